@@ -0,0 +1,15 @@
+//! `libtock_runtime` provides the low-level, architecture-specific pieces of
+//! a Tock process binary: the `RawSyscalls` implementation that actually
+//! traps into the kernel.
+
+#![cfg_attr(any(target_arch = "arm", target_arch = "riscv32"), no_std)]
+
+#[cfg(target_arch = "arm")]
+mod syscalls_impl_arm;
+#[cfg(target_arch = "riscv32")]
+mod syscalls_impl_riscv;
+
+/// The `libtock_platform::RawSyscalls` implementation used by Tock process
+/// binaries on real hardware: issues the architecture's trap instruction
+/// (`svc` on ARM, `ecall` on RISC-V) directly.
+pub struct TockSyscalls;
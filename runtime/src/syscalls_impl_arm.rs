@@ -1,5 +1,6 @@
 use libtock_platform::RawSyscalls;
 
+#[cfg(target_arch = "arm")]
 unsafe impl RawSyscalls for crate::TockSyscalls {
     unsafe fn yield1([r0]: [*mut (); 1]) {
         // Safety: This matches the invariants required by the documentation on
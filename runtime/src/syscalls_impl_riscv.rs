@@ -0,0 +1,104 @@
+use libtock_platform::RawSyscalls;
+
+#[cfg(target_arch = "riscv32")]
+unsafe impl RawSyscalls for crate::TockSyscalls {
+    unsafe fn yield1([r0]: [*mut (); 1]) {
+        // Safety: This matches the invariants required by the documentation on
+        // RawSyscalls::yield1
+        unsafe {
+            asm!("ecall",
+                 in("a4") 0, // Yield class
+                 inlateout("a0") r0 => _,
+                 lateout("a1") _,
+                 lateout("a2") _,
+                 lateout("a3") _,
+                 // a4 carries the syscall class; a5-a7 may be clobbered.
+                 lateout("a5") _,
+                 lateout("a6") _,
+                 lateout("a7") _,
+                 // t0-t6 and ra are caller-saved and may be clobbered by the
+                 // kernel across the trap.
+                 lateout("t0") _,
+                 lateout("t1") _,
+                 lateout("t2") _,
+                 lateout("t3") _,
+                 lateout("t4") _,
+                 lateout("t5") _,
+                 lateout("t6") _,
+                 lateout("ra") _,
+            );
+        }
+    }
+
+    unsafe fn yield2([r0, r1]: [*mut (); 2]) {
+        // Safety: This matches the invariants required by the documentation on
+        // RawSyscalls::yield2
+        unsafe {
+            asm!("ecall",
+                 in("a4") 0, // Yield class
+                 inlateout("a0") r0 => _,
+                 inlateout("a1") r1 => _,
+                 lateout("a2") _,
+                 lateout("a3") _,
+                 lateout("a5") _,
+                 lateout("a6") _,
+                 lateout("a7") _,
+                 lateout("t0") _,
+                 lateout("t1") _,
+                 lateout("t2") _,
+                 lateout("t3") _,
+                 lateout("t4") _,
+                 lateout("t5") _,
+                 lateout("t6") _,
+                 lateout("ra") _,
+            );
+        }
+    }
+
+    unsafe fn syscall1<const CLASS: usize>([mut r0]: [*mut (); 1]) -> [*mut (); 2] {
+        let r1;
+        // Safety: This matches the invariants required by the documentation on
+        // RawSyscalls::syscall1
+        unsafe {
+            asm!("ecall",
+                 in("a4") CLASS,
+                 inlateout("a0") r0,
+                 lateout("a1") r1,
+                 options(preserves_flags, nostack, nomem),
+            );
+        }
+        [r0, r1]
+    }
+
+    unsafe fn syscall2<const CLASS: usize>([mut r0, mut r1]: [*mut (); 2]) -> [*mut (); 2] {
+        // Safety: This matches the invariants required by the documentation on
+        // RawSyscalls::syscall2
+        unsafe {
+            asm!("ecall",
+                 in("a4") CLASS,
+                 inlateout("a0") r0,
+                 inlateout("a1") r1,
+                 options(preserves_flags, nostack, nomem)
+            );
+        }
+        [r0, r1]
+    }
+
+    unsafe fn syscall4<const CLASS: usize>(
+        [mut r0, mut r1, mut r2, mut r3]: [*mut (); 4],
+    ) -> [*mut (); 4] {
+        // Safety: This matches the invariants required by the documentation on
+        // RawSyscalls::syscall4
+        unsafe {
+            asm!("ecall",
+                 in("a4") CLASS,
+                 inlateout("a0") r0,
+                 inlateout("a1") r1,
+                 inlateout("a2") r2,
+                 inlateout("a3") r3,
+                 options(preserves_flags, nostack),
+            );
+        }
+        [r0, r1, r2, r3]
+    }
+}
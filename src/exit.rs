@@ -0,0 +1,93 @@
+//! The Tock 2.0 `Exit` system call (class 6), used to deterministically end
+//! a process rather than falling off the end of `main` into undefined
+//! behavior.
+
+use libtock_platform::RawSyscalls;
+
+const EXIT_CLASS: usize = 6;
+
+// The two `which` identifiers Exit accepts, per TRD104.
+const EXIT_TERMINATE: u32 = 0;
+const EXIT_RESTART: u32 = 1;
+
+/// Tells the kernel this process is done and should not be restarted, and
+/// ends the process with `completion` as its completion code (by
+/// convention, 0 indicates success).
+pub fn terminate(completion: u32) -> ! {
+    exit::<libtock_runtime::TockSyscalls>(EXIT_TERMINATE, completion)
+}
+
+/// Tells the kernel this process should be restarted, reporting `completion`
+/// as the reason.
+pub fn restart(completion: u32) -> ! {
+    exit::<libtock_runtime::TockSyscalls>(EXIT_RESTART, completion)
+}
+
+fn exit<S: RawSyscalls>(which: u32, completion: u32) -> ! {
+    attempt::<S>(which, completion);
+    // Unreachable in practice: the kernel does not resume this process after
+    // Exit. Loop rather than claim to return from a `-> !` function.
+    loop {}
+}
+
+// Issues the Exit system call and returns normally, unlike `exit` above.
+// Meant for callers (e.g. the panic handler) that have their own fallback
+// for the kernel somehow returning control anyway, rather than spinning in
+// an uninformative `loop {}`. Generic over `S` so it can be driven through
+// `libtock_unittest::fake::Kernel` in tests, rather than only the real
+// `libtock_runtime::TockSyscalls`.
+pub(crate) fn attempt<S: RawSyscalls>(which: u32, completion: u32) {
+    // Safety: Exit's ABI takes the exit identifier and a completion code as
+    // its two arguments, in registers r0/r1 on ARM (a0/a1 on RISC-V). The kernel never
+    // returns control to the process after this system call.
+    unsafe {
+        S::syscall2::<EXIT_CLASS>([which as usize as *mut (), completion as usize as *mut ()]);
+    }
+}
+
+pub(crate) fn attempt_terminate(completion: u32) {
+    attempt::<libtock_runtime::TockSyscalls>(EXIT_TERMINATE, completion);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libtock_unittest::{fake, ExpectedSyscall, SyscallLogEntry};
+
+    #[test]
+    fn attempt_issues_exit_with_which_and_completion() {
+        let kernel = fake::Kernel::new("attempt_issues_exit_with_which_and_completion", Vec::new());
+        kernel.add_expected_syscall(ExpectedSyscall::Exit {
+            which: EXIT_RESTART,
+            completion: 7,
+        });
+        attempt::<fake::Kernel>(EXIT_RESTART, 7);
+        assert_eq!(
+            kernel.take_syscall_log(),
+            vec![SyscallLogEntry::Exit {
+                which: EXIT_RESTART,
+                completion: 7,
+            }]
+        );
+    }
+
+    #[test]
+    fn attempt_distinguishes_terminate_from_restart() {
+        let kernel = fake::Kernel::new("attempt_distinguishes_terminate_from_restart", Vec::new());
+        attempt::<fake::Kernel>(EXIT_TERMINATE, 3);
+        attempt::<fake::Kernel>(EXIT_RESTART, 3);
+        assert_eq!(
+            kernel.take_syscall_log(),
+            vec![
+                SyscallLogEntry::Exit {
+                    which: EXIT_TERMINATE,
+                    completion: 3,
+                },
+                SyscallLogEntry::Exit {
+                    which: EXIT_RESTART,
+                    completion: 3,
+                },
+            ]
+        );
+    }
+}
@@ -17,12 +17,20 @@
 //! The final piece is that the entry point of our program, _start, has to call
 //! `rustc_main`. That's covered by the `_start` function in the root of this
 //! crate.
+//!
+//! This module is declared `#[cfg(any(target_arch = "arm", target_arch =
+//! "riscv32"))]` in the crate root (see `entry_point`, which is gated the
+//! same way), so it is never compiled for host `cargo test` runs and can't be
+//! exercised through `libtock_unittest::fake::Kernel` the way `exit` and
+//! `shared_memory` are; it's covered by on-device testing instead.
 
+use crate::console::Console;
 use crate::led;
 use crate::timer;
 use crate::timer::Duration;
 use core::alloc::Layout;
 use core::executor;
+use core::fmt::Write;
 use core::panic::PanicInfo;
 
 #[lang = "start"]
@@ -30,48 +38,108 @@ extern "C" fn start<T>(main: fn() -> T, _argc: isize, _argv: *const *const u8) -
 where
     T: Termination,
 {
-    main();
-    0
+    main().report()
 }
 
 #[lang = "termination"]
-pub trait Termination {}
+pub trait Termination {
+    /// Reports this value to the kernel via the Exit system call. Never
+    /// returns: the process ends (or restarts) as part of reporting.
+    fn report(self) -> !;
+}
 
-impl Termination for () {}
+impl Termination for () {
+    fn report(self) -> ! {
+        crate::exit::terminate(0)
+    }
+}
 
-impl Termination for crate::result::TockResult<()> {}
+impl Termination for crate::result::TockResult<()> {
+    fn report(self) -> ! {
+        match self {
+            Ok(()) => crate::exit::terminate(0),
+            // TODO: Once TockResult's error type carries more information,
+            // derive a more meaningful completion code from it.
+            Err(_) => crate::exit::terminate(1),
+        }
+    }
+}
+
+// Distinct LowLevelDebug status code bases for a Rust panic vs. an
+// allocation failure, so a board with nothing but LowLevelDebug (no
+// console) can still tell the two apart. The panic location's line number
+// is folded into the low bits (mod LINE_NUMBER_MODULUS) to narrow down
+// where execution died.
+const PANIC_STATUS_BASE: u32 = 0x1000_0000;
+const ALLOC_ERROR_STATUS_BASE: u32 = 0x2000_0000;
+const LINE_NUMBER_MODULUS: u32 = PANIC_STATUS_BASE;
+
+// Reports `code` (= `base` with the panic line folded into its low bits) via
+// LowLevelDebug, for boards without a console, and returns it so callers can
+// also include it in a console message.
+fn report_low_level_status(base: u32, line: u32) -> u32 {
+    let code = base | (line % LINE_NUMBER_MODULUS);
+    super::debug::low_level_status_code(code);
+    code
+}
 
 #[panic_handler]
-unsafe fn panic_handler(_info: &PanicInfo) -> ! {
-    // Signal a panic using the LowLevelDebug capsule (if available).
-    super::debug::low_level_status_code(1);
+unsafe fn panic_handler(info: &PanicInfo) -> ! {
+    // Report a LowLevelDebug status code carrying the panic location first,
+    // whether or not a console is present, so boards without one still get it.
+    let line = info.location().map_or(0, |location| location.line());
+    let status = report_low_level_status(PANIC_STATUS_BASE, line);
 
-    // Flash all LEDs (if available).
-    executor::block_on(async {
-        let context = timer::DriverContext::create().ok();
-        let mut driver = context.as_ref().map(|c| c.create_timer_driver_unsafe());
-        let timer_driver = driver.as_mut().and_then(|d| d.activate().ok());
-        loop {
-            for led in led::all() {
-                let _ = led.on();
-            }
-            if let Some(ref timer_driver) = timer_driver {
-                let _ = timer_driver.sleep(Duration::from_ms(100)).await;
-            }
-            for led in led::all() {
-                let _ = led.off();
-            }
-            if let Some(ref timer_driver) = timer_driver {
-                let _ = timer_driver.sleep(Duration::from_ms(100)).await;
-            }
-        }
-    });
-    // Never type is not supported for T in Future
-    unreachable!()
+    // Print the panic location and status code to the console, if one is
+    // present.
+    if let Ok(mut console) = Console::create() {
+        let _ = match info.location() {
+            Some(location) => writeln!(
+                console,
+                "panicked at '{}:{}' (status {:#010x})",
+                location.file(),
+                location.line(),
+                status
+            ),
+            None => writeln!(
+                console,
+                "panicked at <unknown location> (status {:#010x})",
+                status
+            ),
+        };
+    }
+
+    // Having reported as much as we can, ask the kernel to end the process
+    // deterministically.
+    crate::exit::attempt_terminate(status);
+
+    // Final fallback, in case the kernel doesn't act on Exit: flash all
+    // LEDs (whether or not a console or LowLevelDebug was present).
+    blink_forever()
 }
 
 #[alloc_error_handler]
-unsafe fn cycle_leds(_: Layout) -> ! {
+unsafe fn cycle_leds(layout: Layout) -> ! {
+    let status = report_low_level_status(ALLOC_ERROR_STATUS_BASE, 0);
+
+    if let Ok(mut console) = Console::create() {
+        let _ = writeln!(
+            console,
+            "memory allocation of {} bytes failed (status {:#010x})",
+            layout.size(),
+            status
+        );
+    }
+
+    crate::exit::attempt_terminate(status);
+
+    blink_forever()
+}
+
+// Flashes all LEDs forever. The final fallback for reporting a fatal error,
+// reached whenever the Exit attempt returns, whether or not a console or
+// LowLevelDebug capsule is present.
+fn blink_forever() -> ! {
     executor::block_on(async {
         let context = timer::DriverContext::create().ok();
         let mut driver = context.as_ref().map(|c| c.create_timer_driver_unsafe());
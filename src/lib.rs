@@ -17,6 +17,7 @@ pub mod buttons;
 pub mod console;
 pub mod debug;
 pub mod electronics;
+pub mod exit;
 pub mod futures;
 pub mod gpio;
 pub mod led;
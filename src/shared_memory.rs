@@ -0,0 +1,343 @@
+//! Types for sharing process memory with the kernel via the `Allow` system
+//! calls. An `Allow` call swaps a process buffer in for one a capsule
+//! previously had access to (if any), and the wrapper types here revoke the
+//! driver's access to their buffer when dropped, so a shared buffer is never
+//! left allowed for longer than the Rust borrow that created it. Note that
+//! this discards whatever buffer the driver had allowed before `new` was
+//! called, rather than restoring it.
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+use libtock_platform::{ErrorCode, RawSyscalls};
+
+const READ_WRITE_ALLOW: usize = 3;
+const READ_ONLY_ALLOW: usize = 4;
+const ALLOW_USERSPACE_READABLE: usize = 7;
+
+// Decodes an Allow system call's return registers into the buffer that was
+// previously allowed (if any), or the error the kernel reported.
+fn decode_allow_return(registers: [*mut (); 4]) -> Result<(*mut u8, usize), ErrorCode> {
+    let [r0, r1, r2, _r3] = registers;
+    if r0 as usize as u32 == 0 {
+        Err(core::convert::TryFrom::try_from(r1 as usize as u32).unwrap_or(ErrorCode::Fail))
+    } else {
+        Ok((r1 as *mut u8, r2 as usize))
+    }
+}
+
+/// Shares `buffer` with the driver identified by `driver_num` for the
+/// duration of this `ReadWriteAllow`, giving that driver exclusive access.
+/// The driver's access to `buffer` is revoked when this value is dropped.
+///
+/// `S` is the `RawSyscalls` implementation to issue Allow through --
+/// `libtock_runtime::TockSyscalls` on real hardware, or
+/// `libtock_unittest::fake::Kernel` to drive this type through the fake
+/// kernel in tests.
+pub struct ReadWriteAllow<'buffer, S: RawSyscalls> {
+    driver_num: u32,
+    buffer_num: u32,
+    buffer: PhantomData<&'buffer mut [u8]>,
+    syscalls: PhantomData<S>,
+}
+
+impl<'buffer, S: RawSyscalls> ReadWriteAllow<'buffer, S> {
+    /// Shares `buffer` with `driver_num`/`buffer_num`. Panics if the Allow
+    /// system call fails (a capsule rejecting a ReadWriteAllow it supports at
+    /// all indicates a programming error, not a recoverable condition).
+    pub fn new(driver_num: u32, buffer_num: u32, buffer: &'buffer mut [u8]) -> Self {
+        // Safety: ReadWriteAllow's ABI takes the driver number, buffer
+        // number, buffer address, and buffer length, and returns the
+        // previously-shared buffer's address/length (or an error). The
+        // process retains ownership of `buffer` for 'buffer, matching the
+        // lifetime on this `ReadWriteAllow`.
+        let result = unsafe {
+            S::syscall4::<READ_WRITE_ALLOW>([
+                driver_num as usize as *mut (),
+                buffer_num as usize as *mut (),
+                buffer.as_mut_ptr() as *mut (),
+                buffer.len() as *mut (),
+            ])
+        };
+        decode_allow_return(result).expect("ReadWriteAllow rejected by the kernel");
+        ReadWriteAllow {
+            driver_num,
+            buffer_num,
+            buffer: PhantomData,
+            syscalls: PhantomData,
+        }
+    }
+}
+
+impl<'buffer, S: RawSyscalls> Drop for ReadWriteAllow<'buffer, S> {
+    fn drop(&mut self) {
+        // Safety: same as `new`, but passing a null/zero-length buffer to
+        // revoke the driver's access rather than sharing a new buffer.
+        let result = unsafe {
+            S::syscall4::<READ_WRITE_ALLOW>([
+                self.driver_num as usize as *mut (),
+                self.buffer_num as usize as *mut (),
+                core::ptr::null_mut(),
+                core::ptr::null_mut::<()>(),
+            ])
+        };
+        let _ = decode_allow_return(result);
+    }
+}
+
+/// Shares `buffer` with the driver identified by `driver_num` for the
+/// duration of this `ReadOnlyAllow`. The driver's access to `buffer` is
+/// revoked when this value is dropped.
+///
+/// See `ReadWriteAllow` for the purpose of the `S` type parameter.
+pub struct ReadOnlyAllow<'buffer, S: RawSyscalls> {
+    driver_num: u32,
+    buffer_num: u32,
+    buffer: PhantomData<&'buffer [u8]>,
+    syscalls: PhantomData<S>,
+}
+
+impl<'buffer, S: RawSyscalls> ReadOnlyAllow<'buffer, S> {
+    /// Shares `buffer` with `driver_num`/`buffer_num`. Panics if the Allow
+    /// system call fails.
+    pub fn new(driver_num: u32, buffer_num: u32, buffer: &'buffer [u8]) -> Self {
+        // Safety: ReadOnlyAllow's ABI mirrors ReadWriteAllow's, but the
+        // kernel is only granted read access to `buffer`.
+        let result = unsafe {
+            S::syscall4::<READ_ONLY_ALLOW>([
+                driver_num as usize as *mut (),
+                buffer_num as usize as *mut (),
+                buffer.as_ptr() as *mut (),
+                buffer.len() as *mut (),
+            ])
+        };
+        decode_allow_return(result).expect("ReadOnlyAllow rejected by the kernel");
+        ReadOnlyAllow {
+            driver_num,
+            buffer_num,
+            buffer: PhantomData,
+            syscalls: PhantomData,
+        }
+    }
+}
+
+impl<'buffer, S: RawSyscalls> Drop for ReadOnlyAllow<'buffer, S> {
+    fn drop(&mut self) {
+        // Safety: same as ReadWriteAllow::drop.
+        let result = unsafe {
+            S::syscall4::<READ_ONLY_ALLOW>([
+                self.driver_num as usize as *mut (),
+                self.buffer_num as usize as *mut (),
+                core::ptr::null_mut(),
+                core::ptr::null_mut::<()>(),
+            ])
+        };
+        let _ = decode_allow_return(result);
+    }
+}
+
+/// Shares `buffer` with a driver via the "userspace readable" flavor of
+/// ReadWriteAllow: unlike `ReadWriteAllow`, the application keeps a readable
+/// view of the buffer while it is shared, so it can observe the capsule's
+/// writes (e.g. polling streamed sensor data) without having to un-share and
+/// re-share the buffer. Because the kernel may write to the buffer at any
+/// time, it is exposed as `&[Cell<u8>]` rather than `&[u8]`, so reads always
+/// go through a single-byte-at-a-time load instead of relying on a borrow
+/// that the kernel could invalidate.
+///
+/// The driver's access to `buffer` is revoked when this value is dropped.
+/// See `ReadWriteAllow` for the purpose of the `S` type parameter.
+pub struct SharedReadableMemory<'buffer, S: RawSyscalls> {
+    driver_num: u32,
+    buffer_num: u32,
+    buffer: &'buffer [Cell<u8>],
+    syscalls: PhantomData<S>,
+}
+
+impl<'buffer, S: RawSyscalls> SharedReadableMemory<'buffer, S> {
+    /// Shares `buffer` with `driver_num`/`buffer_num`, keeping it readable by
+    /// the application. Panics if the Allow system call fails.
+    pub fn new(driver_num: u32, buffer_num: u32, buffer: &'buffer [Cell<u8>]) -> Self {
+        // Safety: Allow Userspace Readable (system call class 7) takes the
+        // same four arguments as ReadWriteAllow (driver number, buffer
+        // number, address, length), but the kernel leaves the buffer mapped
+        // as readable in the calling process rather than revoking access to
+        // it, which is exactly what lets `read_bytes` below observe the
+        // capsule's writes.
+        let address = buffer.as_ptr() as *mut u8;
+        let result = unsafe {
+            S::syscall4::<ALLOW_USERSPACE_READABLE>([
+                driver_num as usize as *mut (),
+                buffer_num as usize as *mut (),
+                address as *mut (),
+                buffer.len() as *mut (),
+            ])
+        };
+        decode_allow_return(result).expect("Allow Userspace Readable rejected by the kernel");
+        SharedReadableMemory {
+            driver_num,
+            buffer_num,
+            buffer,
+            syscalls: PhantomData,
+        }
+    }
+
+    /// Returns a view of the shared buffer. Reads through this view may
+    /// observe writes the capsule makes at any point while this buffer
+    /// remains shared.
+    pub fn as_slice(&self) -> &[Cell<u8>] {
+        self.buffer
+    }
+
+    /// Copies the current contents of the shared buffer into `out`, which
+    /// must be the same length as the buffer that was shared.
+    pub fn read_bytes(&self, out: &mut [u8]) {
+        assert_eq!(out.len(), self.buffer.len());
+        for (dst, src) in out.iter_mut().zip(self.buffer.iter()) {
+            *dst = src.get();
+        }
+    }
+}
+
+impl<'buffer, S: RawSyscalls> Drop for SharedReadableMemory<'buffer, S> {
+    fn drop(&mut self) {
+        // Safety: same as `new`, but revoking access with a null/zero-length
+        // buffer rather than sharing a new one.
+        let result = unsafe {
+            S::syscall4::<ALLOW_USERSPACE_READABLE>([
+                self.driver_num as usize as *mut (),
+                self.buffer_num as usize as *mut (),
+                core::ptr::null_mut(),
+                core::ptr::null_mut::<()>(),
+            ])
+        };
+        let _ = decode_allow_return(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libtock_unittest::{fake, SyscallLogEntry};
+    use std::rc::Rc;
+
+    // A fake::Driver that accepts any ReadWriteAllow, ReadOnlyAllow, or
+    // Allow Userspace Readable for buffer_num 0, and otherwise behaves like
+    // a capsule that doesn't implement the buffer at all.
+    struct AllowDriver;
+    impl fake::Driver for AllowDriver {
+        fn driver_num(&self) -> u32 {
+            1
+        }
+        fn allow_readwrite(
+            &self,
+            buffer_num: u32,
+            buffer: Option<fake::RwAllowBuffer>,
+        ) -> Result<Option<fake::RwAllowBuffer>, ErrorCode> {
+            if buffer_num == 0 {
+                Ok(buffer)
+            } else {
+                Err(ErrorCode::NoSupport)
+            }
+        }
+        fn allow_readonly(
+            &self,
+            buffer_num: u32,
+            buffer: Option<fake::RoAllowBuffer>,
+        ) -> Result<Option<fake::RoAllowBuffer>, ErrorCode> {
+            if buffer_num == 0 {
+                Ok(buffer)
+            } else {
+                Err(ErrorCode::NoSupport)
+            }
+        }
+        fn allow_userspace_readable(
+            &self,
+            buffer_num: u32,
+            buffer: Option<fake::RwAllowBuffer>,
+        ) -> Result<Option<fake::RwAllowBuffer>, ErrorCode> {
+            if buffer_num == 0 {
+                Ok(buffer)
+            } else {
+                Err(ErrorCode::NoSupport)
+            }
+        }
+    }
+
+    #[test]
+    fn read_write_allow_shares_then_revokes() {
+        let kernel = fake::Kernel::new(
+            "read_write_allow_shares_then_revokes",
+            vec![Rc::new(AllowDriver) as Rc<dyn fake::Driver>],
+        );
+        let mut buffer = [1, 2, 3];
+        {
+            let _allow = ReadWriteAllow::<fake::Kernel>::new(1, 0, &mut buffer);
+        }
+        assert_eq!(
+            kernel.take_syscall_log(),
+            vec![
+                SyscallLogEntry::ReadWriteAllow {
+                    driver_num: 1,
+                    buffer_num: 0,
+                },
+                SyscallLogEntry::ReadWriteAllow {
+                    driver_num: 1,
+                    buffer_num: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn read_only_allow_shares_then_revokes() {
+        let kernel = fake::Kernel::new(
+            "read_only_allow_shares_then_revokes",
+            vec![Rc::new(AllowDriver) as Rc<dyn fake::Driver>],
+        );
+        let buffer = [1, 2, 3];
+        {
+            let _allow = ReadOnlyAllow::<fake::Kernel>::new(1, 0, &buffer);
+        }
+        assert_eq!(
+            kernel.take_syscall_log(),
+            vec![
+                SyscallLogEntry::ReadOnlyAllow {
+                    driver_num: 1,
+                    buffer_num: 0,
+                },
+                SyscallLogEntry::ReadOnlyAllow {
+                    driver_num: 1,
+                    buffer_num: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn shared_readable_memory_reads_capsule_writes() {
+        let kernel = fake::Kernel::new(
+            "shared_readable_memory_reads_capsule_writes",
+            vec![Rc::new(AllowDriver) as Rc<dyn fake::Driver>],
+        );
+        let buffer = [Cell::new(0u8), Cell::new(0u8)];
+        let shared = SharedReadableMemory::<fake::Kernel>::new(1, 0, &buffer);
+        buffer[0].set(42);
+        let mut out = [0u8; 2];
+        shared.read_bytes(&mut out);
+        assert_eq!(out, [42, 0]);
+        drop(shared);
+        assert_eq!(
+            kernel.take_syscall_log(),
+            vec![
+                SyscallLogEntry::AllowUserspaceReadable {
+                    driver_num: 1,
+                    buffer_num: 0,
+                },
+                SyscallLogEntry::AllowUserspaceReadable {
+                    driver_num: 1,
+                    buffer_num: 0,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::kernel::{RoAllowBuffer, RwAllowBuffer, Upcall};
+use libtock_platform::{CommandReturn, ErrorCode};
+
+/// An anticipated upcoming system call. Used with
+/// `Kernel::add_expected_syscall` to assert that a test makes the system
+/// calls it is expected to, and/or to inject an artificial return value (e.g.
+/// simulating an error a fake::Driver has no other way to produce).
+///
+/// Each variant's fields other than `override_return` are compared against
+/// the actual system call made; a mismatch panics with a diff of the
+/// expected and actual calls. When `override_return` is `Some`, it is
+/// returned in place of actually routing the call to the registered
+/// fake::Driver.
+#[derive(Debug)]
+pub enum ExpectedSyscall {
+    Yield,
+    Subscribe {
+        driver_num: u32,
+        subscribe_num: u32,
+        upcall: Option<Upcall>,
+        override_return: Option<Result<Option<Upcall>, ErrorCode>>,
+    },
+    Command {
+        driver_num: u32,
+        command_num: u32,
+        argument0: u32,
+        argument1: u32,
+        override_return: Option<CommandReturn>,
+    },
+    ReadOnlyAllow {
+        driver_num: u32,
+        buffer_num: u32,
+        buffer: Option<RoAllowBuffer>,
+        override_return: Option<Result<Option<RoAllowBuffer>, ErrorCode>>,
+    },
+    ReadWriteAllow {
+        driver_num: u32,
+        buffer_num: u32,
+        buffer: Option<RwAllowBuffer>,
+        override_return: Option<Result<Option<RwAllowBuffer>, ErrorCode>>,
+    },
+    AllowUserspaceReadable {
+        driver_num: u32,
+        buffer_num: u32,
+        buffer: Option<RwAllowBuffer>,
+        override_return: Option<Result<Option<RwAllowBuffer>, ErrorCode>>,
+    },
+    Memop {
+        op: u32,
+        override_return: Option<u32>,
+    },
+    Exit {
+        which: u32,
+        completion: u32,
+    },
+}
+
+impl ExpectedSyscall {
+    // Returns whether `self` and `actual` describe the same system call
+    // (same class and same arguments), ignoring `self`'s `override_return`
+    // (an actual call has no such field to compare against).
+    pub(crate) fn same_call(&self, actual: &ExpectedSyscall) -> bool {
+        use ExpectedSyscall::*;
+        match (self, actual) {
+            (Yield, Yield) => true,
+            (
+                Subscribe {
+                    driver_num: d1,
+                    subscribe_num: s1,
+                    upcall: u1,
+                    ..
+                },
+                Subscribe {
+                    driver_num: d2,
+                    subscribe_num: s2,
+                    upcall: u2,
+                    ..
+                },
+            ) => d1 == d2 && s1 == s2 && u1 == u2,
+            (
+                Command {
+                    driver_num: d1,
+                    command_num: c1,
+                    argument0: a1,
+                    argument1: b1,
+                    ..
+                },
+                Command {
+                    driver_num: d2,
+                    command_num: c2,
+                    argument0: a2,
+                    argument1: b2,
+                    ..
+                },
+            ) => d1 == d2 && c1 == c2 && a1 == a2 && b1 == b2,
+            (
+                ReadOnlyAllow {
+                    driver_num: d1,
+                    buffer_num: b1,
+                    buffer: buf1,
+                    ..
+                },
+                ReadOnlyAllow {
+                    driver_num: d2,
+                    buffer_num: b2,
+                    buffer: buf2,
+                    ..
+                },
+            ) => d1 == d2 && b1 == b2 && buf1 == buf2,
+            (
+                ReadWriteAllow {
+                    driver_num: d1,
+                    buffer_num: b1,
+                    buffer: buf1,
+                    ..
+                },
+                ReadWriteAllow {
+                    driver_num: d2,
+                    buffer_num: b2,
+                    buffer: buf2,
+                    ..
+                },
+            ) => d1 == d2 && b1 == b2 && buf1 == buf2,
+            (
+                AllowUserspaceReadable {
+                    driver_num: d1,
+                    buffer_num: b1,
+                    buffer: buf1,
+                    ..
+                },
+                AllowUserspaceReadable {
+                    driver_num: d2,
+                    buffer_num: b2,
+                    buffer: buf2,
+                    ..
+                },
+            ) => d1 == d2 && b1 == b2 && buf1 == buf2,
+            (Memop { op: o1, .. }, Memop { op: o2, .. }) => o1 == o2,
+            (
+                Exit {
+                    which: w1,
+                    completion: c1,
+                },
+                Exit {
+                    which: w2,
+                    completion: c2,
+                },
+            ) => w1 == w2 && c1 == c2,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use libtock_platform::{CommandReturn, ErrorCode};
+
+/// A process buffer shared with a driver via a read-only Allow system call.
+/// Wraps the raw address/length pair exactly as it crosses the system call
+/// boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoAllowBuffer {
+    pub(crate) address: *const u8,
+    pub(crate) len: usize,
+}
+
+/// A process buffer shared with a driver via a read-write Allow system call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RwAllowBuffer {
+    pub(crate) address: *mut u8,
+    pub(crate) len: usize,
+}
+
+/// An upcall registered with a driver via a Subscribe system call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Upcall {
+    pub(crate) upcall_fn: *const (),
+    pub(crate) data: *mut (),
+}
+
+/// A fake implementation of a Tock capsule's system call interface, modeled
+/// on the interface capsules present to the real Tock kernel. `fake::Kernel`
+/// routes `Command`, `Subscribe`, and the three `Allow` system calls
+/// (read-only, read-write, and userspace-readable) to the
+/// `Driver` registered under the corresponding driver number; `Yield`,
+/// `Memop`, and `Exit` are handled directly by `Kernel` and never reach a
+/// `Driver`.
+///
+/// Implementations only need to override the system calls they actually
+/// support; unsupported system calls fail with `ErrorCode::NoSupport` by
+/// default, matching the behavior of a real capsule that doesn't implement a
+/// particular `command_num`/`subscribe_num`/`buffer_num`.
+pub trait Driver {
+    /// The driver number this fake driver responds to.
+    fn driver_num(&self) -> u32;
+
+    /// Simulates a `Command` system call.
+    fn command(&self, command_num: u32, argument0: u32, argument1: u32) -> CommandReturn {
+        let _ = (command_num, argument0, argument1);
+        CommandReturn::failure(ErrorCode::NoSupport)
+    }
+
+    /// Simulates a `ReadOnlyAllow` system call, swapping `buffer` in for
+    /// `buffer_num` and returning the buffer that was previously allowed (if
+    /// any).
+    fn allow_readonly(
+        &self,
+        buffer_num: u32,
+        buffer: Option<RoAllowBuffer>,
+    ) -> Result<Option<RoAllowBuffer>, ErrorCode> {
+        let _ = (buffer_num, buffer);
+        Err(ErrorCode::NoSupport)
+    }
+
+    /// Simulates a `ReadWriteAllow` system call, swapping `buffer` in for
+    /// `buffer_num` and returning the buffer that was previously allowed (if
+    /// any).
+    fn allow_readwrite(
+        &self,
+        buffer_num: u32,
+        buffer: Option<RwAllowBuffer>,
+    ) -> Result<Option<RwAllowBuffer>, ErrorCode> {
+        let _ = (buffer_num, buffer);
+        Err(ErrorCode::NoSupport)
+    }
+
+    /// Simulates an "Allow Userspace Readable" system call, swapping `buffer`
+    /// in for `buffer_num` and returning the buffer that was previously
+    /// allowed (if any). Unlike `allow_readwrite`, a real kernel leaves the
+    /// buffer mapped as readable in the calling process for the duration of
+    /// the Allow, but that distinction doesn't affect how a `fake::Driver`
+    /// sees the buffer here.
+    fn allow_userspace_readable(
+        &self,
+        buffer_num: u32,
+        buffer: Option<RwAllowBuffer>,
+    ) -> Result<Option<RwAllowBuffer>, ErrorCode> {
+        let _ = (buffer_num, buffer);
+        Err(ErrorCode::NoSupport)
+    }
+
+    /// Simulates a `Subscribe` system call, swapping `upcall` in for
+    /// `subscribe_num` and returning the upcall that was previously
+    /// registered (if any).
+    fn subscribe(
+        &self,
+        subscribe_num: u32,
+        upcall: Option<Upcall>,
+    ) -> Result<Option<Upcall>, ErrorCode> {
+        let _ = (subscribe_num, upcall);
+        Err(ErrorCode::NoSupport)
+    }
+}
@@ -1,14 +1,13 @@
 use crate::{ExpectedSyscall, SyscallLogEntry};
 use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
-// TODO: Add Allow.
-// TODO: Add Command.
-// TODO: Add Exit.
-// TODO: Add Memop.
-// TODO: Add Subscribe.
+mod driver;
 mod raw_syscalls_impl;
 mod thread_local;
-// TODO: Add Yield.
+
+pub use driver::{Driver, RoAllowBuffer, RwAllowBuffer, Upcall};
 
 /// A fake implementation of the Tock kernel. Provides
 /// `libtock_platform::Syscalls` by implementing
@@ -20,9 +19,8 @@ mod thread_local;
 /// As such, test code is given a `Rc<Kernel>` rather than a `Kernel` instance
 /// directly. Because `Rc` is a shared reference, Kernel extensively uses
 /// internal mutability.
-// TODO: Define the `fake::Driver` trait and add support for fake drivers in
-// Kernel.
 pub struct Kernel {
+    drivers: HashMap<u32, Rc<dyn Driver>>,
     expected_syscalls: Cell<std::collections::VecDeque<ExpectedSyscall>>,
     name: &'static str,
     syscall_log: Cell<Vec<SyscallLogEntry>>,
@@ -32,9 +30,27 @@ impl Kernel {
     /// Creates a `Kernel` for this thread and returns a reference to it. This
     /// instance should be dropped at the end of the test, before this thread
     /// creates another `Kernel`. `name` should be a string identifying the test
-    /// case, and is used to provide better diagnostics.
-    pub fn new(name: &'static str) -> std::rc::Rc<Kernel> {
+    /// case, and is used to provide better diagnostics. `drivers` are attached
+    /// to the `Kernel` and will receive any system call naming their
+    /// `driver_num`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if two entries of `drivers` report the same `driver_num()`, as
+    /// a real kernel never has two capsules sharing a driver number.
+    pub fn new(name: &'static str, drivers: Vec<Rc<dyn Driver>>) -> std::rc::Rc<Kernel> {
+        let mut driver_map = HashMap::with_capacity(drivers.len());
+        for driver in drivers {
+            let driver_num = driver.driver_num();
+            if driver_map.insert(driver_num, driver).is_some() {
+                panic!(
+                    "fake::Kernel '{}' was given two Drivers with driver_num {}",
+                    name, driver_num
+                );
+            }
+        }
         let rc = std::rc::Rc::new(Kernel {
+            drivers: driver_map,
             expected_syscalls: Default::default(),
             name,
             syscall_log: Default::default(),
@@ -82,8 +98,12 @@ impl Drop for Kernel {
 // -----------------------------------------------------------------------------
 
 impl Kernel {
+    // Looks up the fake::Driver registered for `driver_num`, if any.
+    pub(crate) fn driver(&self, driver_num: u32) -> Option<Rc<dyn Driver>> {
+        self.drivers.get(&driver_num).cloned()
+    }
+
     // Appends a log entry to the system call queue.
-    #[allow(unused)] // TODO: Remove when a system call is implemented.
     fn log_syscall(&self, syscall: SyscallLogEntry) {
         let mut log = self.syscall_log.take();
         log.push(syscall);
@@ -92,7 +112,6 @@ impl Kernel {
 
     // Retrieves the first syscall in the expected syscalls queue, removing it
     // from the queue. Returns None if the queue was empty.
-    #[allow(unused)] // TODO: Remove when a system call is implemented.
     fn pop_expected_syscall(&self) -> Option<ExpectedSyscall> {
         let mut queue = self.expected_syscalls.take();
         let expected_syscall = queue.pop_front();
@@ -115,13 +134,14 @@ impl Kernel {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use libtock_platform::{CommandReturn, ErrorCode};
 
     // Verifies the name propagates correctly into the report_leaked() error
     // message.
     #[test]
     fn name_to_report_leaked() {
         let result = std::panic::catch_unwind(|| {
-            Kernel::new("name_to_report_leaked").report_leaked();
+            Kernel::new("name_to_report_leaked", Vec::new()).report_leaked();
         });
         let panic_arg = result.expect_err("Kernel::report_leaked did not panic");
         let message = panic_arg
@@ -130,8 +150,179 @@ mod tests {
         assert!(message.contains("name_to_report_leaked"));
     }
 
-    // TODO: We cannot currently test the expected syscall queue or the syscall
-    // log, because ExpectedSyscall and SyscallLogEntry are currently
-    // uninhabited types. When we implement a system call, we should add tests
-    // for that functionality as well.
+    // A trivial fake::Driver used to exercise the expected syscall queue and
+    // the syscall log.
+    struct CommandDriver;
+    impl Driver for CommandDriver {
+        fn driver_num(&self) -> u32 {
+            1
+        }
+        fn command(&self, _command_num: u32, _argument0: u32, _argument1: u32) -> CommandReturn {
+            CommandReturn::success()
+        }
+    }
+
+    fn command_registers(driver_num: u32, command_num: u32, a0: u32, a1: u32) -> [*mut (); 4] {
+        [
+            driver_num as usize as *mut (),
+            command_num as usize as *mut (),
+            a0 as usize as *mut (),
+            a1 as usize as *mut (),
+        ]
+    }
+
+    // The Command syscall class, as used by raw_syscalls_impl's `route`.
+    const COMMAND: usize = 2;
+
+    // Verifies that a Command call matching the expected syscall queue is
+    // routed to the driver as normal, and is recorded in the syscall log.
+    #[test]
+    fn expected_syscall_match() {
+        let kernel = Kernel::new(
+            "expected_syscall_match",
+            vec![Rc::new(CommandDriver) as Rc<dyn Driver>],
+        );
+        kernel.add_expected_syscall(ExpectedSyscall::Command {
+            driver_num: 1,
+            command_num: 2,
+            argument0: 3,
+            argument1: 4,
+            override_return: None,
+        });
+        // Safety: this matches the documented ABI for Command, and CommandDriver
+        // does not have any additional safety invariants.
+        unsafe {
+            <Kernel as libtock_platform::RawSyscalls>::syscall4::<COMMAND>(command_registers(
+                1, 2, 3, 4,
+            ));
+        }
+        assert_eq!(
+            kernel.take_syscall_log(),
+            vec![SyscallLogEntry::Command {
+                driver_num: 1,
+                command_num: 2,
+                argument0: 3,
+                argument1: 4,
+            }]
+        );
+    }
+
+    // Verifies that a Command call that does not match the head of the
+    // expected syscall queue panics.
+    #[test]
+    fn expected_syscall_mismatch_panics() {
+        let kernel = Kernel::new(
+            "expected_syscall_mismatch_panics",
+            vec![Rc::new(CommandDriver) as Rc<dyn Driver>],
+        );
+        kernel.add_expected_syscall(ExpectedSyscall::Command {
+            driver_num: 1,
+            command_num: 2,
+            argument0: 3,
+            argument1: 4,
+            override_return: None,
+        });
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            // Safety: this matches the documented ABI for Command.
+            unsafe {
+                <Kernel as libtock_platform::RawSyscalls>::syscall4::<COMMAND>(command_registers(
+                    1, 2, 3, 5, // argument1 differs from the expectation above.
+                ));
+            }
+        }));
+        let panic_arg = result.expect_err("mismatched Command call did not panic");
+        let message = panic_arg
+            .downcast_ref::<String>()
+            .expect("Wrong panic payload type");
+        assert!(message.contains("expected_syscall_mismatch_panics"));
+    }
+
+    // Verifies that an expected syscall's override_return is used instead of
+    // invoking the driver.
+    #[test]
+    fn expected_syscall_override_return() {
+        let kernel = Kernel::new(
+            "expected_syscall_override_return",
+            vec![Rc::new(CommandDriver) as Rc<dyn Driver>],
+        );
+        kernel.add_expected_syscall(ExpectedSyscall::Command {
+            driver_num: 1,
+            command_num: 2,
+            argument0: 3,
+            argument1: 4,
+            override_return: Some(CommandReturn::failure(ErrorCode::Busy)),
+        });
+        // Safety: this matches the documented ABI for Command.
+        let [r0, r1, _, _] = unsafe {
+            <Kernel as libtock_platform::RawSyscalls>::syscall4::<COMMAND>(command_registers(
+                1, 2, 3, 4,
+            ))
+        };
+        // Return variant 0 (Failure) with ErrorCode::Busy in r1, matching
+        // CommandReturn::failure's encoding rather than CommandDriver's
+        // CommandReturn::success().
+        assert_eq!(r0 as usize as u32, 0);
+        assert_eq!(r1 as usize as u32, ErrorCode::Busy as u32);
+    }
+
+    // A trivial fake::Driver used to exercise Subscribe's return encoding.
+    struct SubscribeDriver;
+    impl Driver for SubscribeDriver {
+        fn driver_num(&self) -> u32 {
+            2
+        }
+        fn subscribe(
+            &self,
+            _subscribe_num: u32,
+            upcall: Option<Upcall>,
+        ) -> Result<Option<Upcall>, ErrorCode> {
+            Ok(upcall)
+        }
+    }
+
+    fn subscribe_registers(
+        driver_num: u32,
+        subscribe_num: u32,
+        upcall_fn: *const (),
+        data: *mut (),
+    ) -> [*mut (); 4] {
+        [
+            driver_num as usize as *mut (),
+            subscribe_num as usize as *mut (),
+            upcall_fn as *mut (),
+            data,
+        ]
+    }
+
+    // The Subscribe syscall class, as used by raw_syscalls_impl's `route`.
+    const SUBSCRIBE: usize = 1;
+
+    // Verifies that a successful Subscribe call is encoded with TRD104's
+    // "success with two u32" return variant (6), and is recorded in the
+    // syscall log, rather than being mistagged as a Failure.
+    #[test]
+    fn subscribe_encodes_success_with_two_u32() {
+        let kernel = Kernel::new(
+            "subscribe_encodes_success_with_two_u32",
+            vec![Rc::new(SubscribeDriver) as Rc<dyn Driver>],
+        );
+        // Safety: this matches the documented ABI for Subscribe, and
+        // SubscribeDriver does not have any additional safety invariants.
+        let [r0, r1, r2, _r3] =
+            unsafe {
+                <Kernel as libtock_platform::RawSyscalls>::syscall4::<SUBSCRIBE>(
+                    subscribe_registers(2, 3, core::ptr::null(), core::ptr::null_mut()),
+                )
+            };
+        assert_eq!(r0 as usize as u32, 6);
+        assert_eq!(r1 as usize as u32, 0);
+        assert_eq!(r2 as usize as u32, 0);
+        assert_eq!(
+            kernel.take_syscall_log(),
+            vec![SyscallLogEntry::Subscribe {
+                driver_num: 2,
+                subscribe_num: 3,
+            }]
+        );
+    }
 }
@@ -0,0 +1,375 @@
+//! Implements `libtock_platform::RawSyscalls` for `Kernel` by decoding the
+//! syscall class and driver number out of the incoming registers, comparing
+//! against the expected syscall queue, routing the call to the registered
+//! `Driver` (if any, and unless overridden), and encoding the response back
+//! into the outgoing registers -- i.e. this is the fake equivalent of the
+//! ARM/RISC-V `svc`/`ecall` trap handlers.
+
+use super::thread_local::with_kernel;
+use super::{Kernel, RoAllowBuffer, RwAllowBuffer, Upcall};
+use crate::{ExpectedSyscall, SyscallLogEntry};
+use libtock_platform::{CommandReturn, ErrorCode, RawSyscalls};
+
+// Syscall classes, as defined by the Tock ABI (TRD104).
+const SUBSCRIBE: usize = 1;
+const COMMAND: usize = 2;
+const READ_WRITE_ALLOW: usize = 3;
+const READ_ONLY_ALLOW: usize = 4;
+const MEMOP: usize = 5;
+const EXIT: usize = 6;
+const ALLOW_USERSPACE_READABLE: usize = 7;
+
+// Return variant identifiers, as defined by the Tock ABI (TRD104). These are
+// shared across all system call classes, so a Failure can never be mistaken
+// for a Success regardless of which syscall produced it. Only the variants
+// fake::Kernel actually produces are named here.
+const FAILURE: u32 = 0;
+const SUCCESS_WITH_U32: u32 = 5;
+const SUCCESS_WITH_TWO_U32: u32 = 6;
+
+fn encode(value: u32) -> *mut () {
+    value as usize as *mut ()
+}
+
+fn decode(register: *mut ()) -> u32 {
+    register as usize as u32
+}
+
+fn failure(error: ErrorCode) -> [*mut (); 4] {
+    [encode(FAILURE), encode(error as u32), encode(0), encode(0)]
+}
+
+// The failure a real kernel returns for a system call naming a driver number
+// that has no capsule (here, no registered fake::Driver) behind it.
+fn nodevice() -> [*mut (); 4] {
+    failure(ErrorCode::NoDevice)
+}
+
+fn encode_allow_readwrite(result: Result<Option<RwAllowBuffer>, ErrorCode>) -> [*mut (); 4] {
+    match result {
+        Err(error) => failure(error),
+        Ok(None) => [
+            encode(SUCCESS_WITH_TWO_U32),
+            encode(0),
+            encode(0),
+            encode(0),
+        ],
+        Ok(Some(buffer)) => [
+            encode(SUCCESS_WITH_TWO_U32),
+            buffer.address as *mut (),
+            encode(buffer.len as u32),
+            encode(0),
+        ],
+    }
+}
+
+fn encode_allow_readonly(result: Result<Option<RoAllowBuffer>, ErrorCode>) -> [*mut (); 4] {
+    match result {
+        Err(error) => failure(error),
+        Ok(None) => [
+            encode(SUCCESS_WITH_TWO_U32),
+            encode(0),
+            encode(0),
+            encode(0),
+        ],
+        Ok(Some(buffer)) => [
+            encode(SUCCESS_WITH_TWO_U32),
+            buffer.address as *mut u8 as *mut (),
+            encode(buffer.len as u32),
+            encode(0),
+        ],
+    }
+}
+
+fn encode_subscribe(result: Result<Option<Upcall>, ErrorCode>) -> [*mut (); 4] {
+    match result {
+        Err(error) => failure(error),
+        Ok(None) => [
+            encode(SUCCESS_WITH_TWO_U32),
+            encode(0),
+            encode(0),
+            encode(0),
+        ],
+        Ok(Some(upcall)) => [
+            encode(SUCCESS_WITH_TWO_U32),
+            upcall.upcall_fn as *mut (),
+            upcall.data,
+            encode(0),
+        ],
+    }
+}
+
+// Compares `actual` with the head of the expected syscall queue (if any),
+// panicking with a diff naming `kernel` if they don't describe the same
+// call. Returns the entry to take the (possible) override_return from: the
+// popped expectation if the queue was nonempty, or `actual` (whose
+// override_return is always None) otherwise.
+fn check_expected(kernel: &Kernel, actual: ExpectedSyscall) -> ExpectedSyscall {
+    match kernel.pop_expected_syscall() {
+        None => actual,
+        Some(expected) => {
+            assert!(
+                expected.same_call(&actual),
+                "fake::Kernel '{}': expected syscall {:?}, but got {:?}",
+                kernel.name,
+                expected,
+                actual,
+            );
+            expected
+        }
+    }
+}
+
+// SAFETY: Kernel's implementations of the RawSyscalls methods don't actually
+// invoke `svc`/`ecall`; they just look up a fake::Driver and call into it, so
+// none of the safety invariants documented on RawSyscalls are relevant here.
+unsafe impl RawSyscalls for Kernel {
+    unsafe fn yield1(_args: [*mut (); 1]) {
+        with_kernel(|kernel| {
+            check_expected(kernel, ExpectedSyscall::Yield);
+            kernel.log_syscall(SyscallLogEntry::Yield);
+        });
+    }
+
+    unsafe fn yield2(_args: [*mut (); 2]) {
+        with_kernel(|kernel| {
+            check_expected(kernel, ExpectedSyscall::Yield);
+            kernel.log_syscall(SyscallLogEntry::Yield);
+        });
+    }
+
+    unsafe fn syscall1<const CLASS: usize>([r0]: [*mut (); 1]) -> [*mut (); 2] {
+        let [r0, r1, ..] = route(CLASS, [r0, encode(0), encode(0), encode(0)]);
+        [r0, r1]
+    }
+
+    unsafe fn syscall2<const CLASS: usize>([r0, r1]: [*mut (); 2]) -> [*mut (); 2] {
+        let [r0, r1, ..] = route(CLASS, [r0, r1, encode(0), encode(0)]);
+        [r0, r1]
+    }
+
+    unsafe fn syscall4<const CLASS: usize>(args: [*mut (); 4]) -> [*mut (); 4] {
+        route(CLASS, args)
+    }
+}
+
+// Routes a non-Yield system call to the registered fake::Driver, or handles
+// it directly if it's a class Kernel services itself (Memop, Exit).
+fn route(class: usize, [r0, r1, r2, r3]: [*mut (); 4]) -> [*mut (); 4] {
+    match class {
+        SUBSCRIBE => with_kernel(|kernel| {
+            let driver_num = decode(r0);
+            let subscribe_num = decode(r1);
+            let upcall = if r2.is_null() {
+                None
+            } else {
+                Some(Upcall {
+                    upcall_fn: r2 as *const (),
+                    data: r3,
+                })
+            };
+            let expected = check_expected(
+                kernel,
+                ExpectedSyscall::Subscribe {
+                    driver_num,
+                    subscribe_num,
+                    upcall,
+                    override_return: None,
+                },
+            );
+            kernel.log_syscall(SyscallLogEntry::Subscribe {
+                driver_num,
+                subscribe_num,
+            });
+            let override_return = match expected {
+                ExpectedSyscall::Subscribe {
+                    override_return, ..
+                } => override_return,
+                _ => None,
+            };
+            encode_subscribe(
+                override_return.unwrap_or_else(|| match kernel.driver(driver_num) {
+                    None => Err(ErrorCode::NoDevice),
+                    Some(driver) => driver.subscribe(subscribe_num, upcall),
+                }),
+            )
+        }),
+        COMMAND => with_kernel(|kernel| {
+            let driver_num = decode(r0);
+            let command_num = decode(r1);
+            let argument0 = decode(r2);
+            let argument1 = decode(r3);
+            let expected = check_expected(
+                kernel,
+                ExpectedSyscall::Command {
+                    driver_num,
+                    command_num,
+                    argument0,
+                    argument1,
+                    override_return: None,
+                },
+            );
+            kernel.log_syscall(SyscallLogEntry::Command {
+                driver_num,
+                command_num,
+                argument0,
+                argument1,
+            });
+            let override_return = match expected {
+                ExpectedSyscall::Command {
+                    override_return, ..
+                } => override_return,
+                _ => None,
+            };
+            let command_return =
+                override_return.unwrap_or_else(|| match kernel.driver(driver_num) {
+                    None => CommandReturn::failure(ErrorCode::NoDevice),
+                    Some(driver) => driver.command(command_num, argument0, argument1),
+                });
+            let [a, b, c, d] = command_return.raw_values();
+            [encode(a), encode(b), encode(c), encode(d)]
+        }),
+        READ_WRITE_ALLOW => with_kernel(|kernel| {
+            let driver_num = decode(r0);
+            let buffer_num = decode(r1);
+            let buffer = if r2.is_null() {
+                None
+            } else {
+                Some(RwAllowBuffer {
+                    address: r2 as *mut u8,
+                    len: decode(r3) as usize,
+                })
+            };
+            let expected = check_expected(
+                kernel,
+                ExpectedSyscall::ReadWriteAllow {
+                    driver_num,
+                    buffer_num,
+                    buffer,
+                    override_return: None,
+                },
+            );
+            kernel.log_syscall(SyscallLogEntry::ReadWriteAllow {
+                driver_num,
+                buffer_num,
+            });
+            let override_return = match expected {
+                ExpectedSyscall::ReadWriteAllow {
+                    override_return, ..
+                } => override_return,
+                _ => None,
+            };
+            encode_allow_readwrite(override_return.unwrap_or_else(
+                || match kernel.driver(driver_num) {
+                    None => Err(ErrorCode::NoDevice),
+                    Some(driver) => driver.allow_readwrite(buffer_num, buffer),
+                },
+            ))
+        }),
+        READ_ONLY_ALLOW => with_kernel(|kernel| {
+            let driver_num = decode(r0);
+            let buffer_num = decode(r1);
+            let buffer = if r2.is_null() {
+                None
+            } else {
+                Some(RoAllowBuffer {
+                    address: r2 as *const u8,
+                    len: decode(r3) as usize,
+                })
+            };
+            let expected = check_expected(
+                kernel,
+                ExpectedSyscall::ReadOnlyAllow {
+                    driver_num,
+                    buffer_num,
+                    buffer,
+                    override_return: None,
+                },
+            );
+            kernel.log_syscall(SyscallLogEntry::ReadOnlyAllow {
+                driver_num,
+                buffer_num,
+            });
+            let override_return = match expected {
+                ExpectedSyscall::ReadOnlyAllow {
+                    override_return, ..
+                } => override_return,
+                _ => None,
+            };
+            encode_allow_readonly(override_return.unwrap_or_else(
+                || match kernel.driver(driver_num) {
+                    None => Err(ErrorCode::NoDevice),
+                    Some(driver) => driver.allow_readonly(buffer_num, buffer),
+                },
+            ))
+        }),
+        ALLOW_USERSPACE_READABLE => with_kernel(|kernel| {
+            let driver_num = decode(r0);
+            let buffer_num = decode(r1);
+            let buffer = if r2.is_null() {
+                None
+            } else {
+                Some(RwAllowBuffer {
+                    address: r2 as *mut u8,
+                    len: decode(r3) as usize,
+                })
+            };
+            let expected = check_expected(
+                kernel,
+                ExpectedSyscall::AllowUserspaceReadable {
+                    driver_num,
+                    buffer_num,
+                    buffer,
+                    override_return: None,
+                },
+            );
+            kernel.log_syscall(SyscallLogEntry::AllowUserspaceReadable {
+                driver_num,
+                buffer_num,
+            });
+            let override_return = match expected {
+                ExpectedSyscall::AllowUserspaceReadable {
+                    override_return, ..
+                } => override_return,
+                _ => None,
+            };
+            encode_allow_readwrite(override_return.unwrap_or_else(
+                || match kernel.driver(driver_num) {
+                    None => Err(ErrorCode::NoDevice),
+                    Some(driver) => driver.allow_userspace_readable(buffer_num, buffer),
+                },
+            ))
+        }),
+        MEMOP => with_kernel(|kernel| {
+            let op = decode(r0);
+            let expected = check_expected(
+                kernel,
+                ExpectedSyscall::Memop {
+                    op,
+                    override_return: None,
+                },
+            );
+            kernel.log_syscall(SyscallLogEntry::Memop { op });
+            let override_return = match expected {
+                ExpectedSyscall::Memop {
+                    override_return, ..
+                } => override_return,
+                _ => None,
+            };
+            [
+                encode(SUCCESS_WITH_U32),
+                encode(override_return.unwrap_or(0)),
+                encode(0),
+                encode(0),
+            ]
+        }),
+        EXIT => with_kernel(|kernel| {
+            let which = decode(r0);
+            let completion = decode(r1);
+            check_expected(kernel, ExpectedSyscall::Exit { which, completion });
+            kernel.log_syscall(SyscallLogEntry::Exit { which, completion });
+            [encode(0), encode(0), encode(0), encode(0)]
+        }),
+        _ => nodevice(),
+    }
+}
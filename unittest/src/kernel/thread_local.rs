@@ -0,0 +1,42 @@
+//! Associates the current thread with (at most) one live `fake::Kernel`, so
+//! that `Kernel`'s `RawSyscalls` implementation (which has no access to the
+//! `Rc<Kernel>` a test holds) can find its way back to the right `Kernel`.
+
+use super::Kernel;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+thread_local! {
+    static KERNEL: RefCell<Weak<Kernel>> = RefCell::new(Weak::new());
+}
+
+// Registers `kernel` as this thread's fake::Kernel. Panics if this thread
+// already has a live Kernel, as only one Kernel may exist per thread.
+pub(super) fn set_kernel(kernel: &Rc<Kernel>) {
+    KERNEL.with(|cell| {
+        let mut current = cell.borrow_mut();
+        if current.upgrade().is_some() {
+            panic!("Attempted to create more than one fake::Kernel on the same thread");
+        }
+        *current = Rc::downgrade(kernel);
+    });
+}
+
+// Removes this thread's association with its fake::Kernel. Called from
+// Kernel::drop.
+pub(super) fn clear_kernel() {
+    KERNEL.with(|cell| *cell.borrow_mut() = Weak::new());
+}
+
+// Runs `op` with a reference to this thread's fake::Kernel. Panics if this
+// thread does not currently have a live Kernel, which indicates a system call
+// was made without a Kernel present (e.g. the Kernel was dropped too early).
+pub(super) fn with_kernel<R>(op: impl FnOnce(&Kernel) -> R) -> R {
+    KERNEL.with(|cell| {
+        let kernel = cell
+            .borrow()
+            .upgrade()
+            .expect("Tock system call made without an active fake::Kernel");
+        op(&kernel)
+    })
+}
@@ -0,0 +1,19 @@
+//! `libtock_unittest` allows capsule-facing code built on top of
+//! `libtock_platform` to be unit tested on the host, by providing a fake
+//! implementation of the Tock kernel's system call interface.
+//!
+//! The primary entry point is [`fake::Kernel`], which implements
+//! `libtock_platform::RawSyscalls` and routes system calls to
+//! [`fake::Driver`] implementations registered with it.
+
+mod expected_syscall;
+mod kernel;
+mod syscall_log_entry;
+
+pub use expected_syscall::ExpectedSyscall;
+pub use syscall_log_entry::SyscallLogEntry;
+
+/// Fakes that stand in for the Tock kernel and its capsules in unit tests.
+pub mod fake {
+    pub use crate::kernel::{Driver, Kernel, RoAllowBuffer, RwAllowBuffer, Upcall};
+}
@@ -0,0 +1,35 @@
+/// A record of a single system call made through a `fake::Kernel`, as
+/// returned by `Kernel::take_syscall_log`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyscallLogEntry {
+    Yield,
+    Subscribe {
+        driver_num: u32,
+        subscribe_num: u32,
+    },
+    Command {
+        driver_num: u32,
+        command_num: u32,
+        argument0: u32,
+        argument1: u32,
+    },
+    ReadOnlyAllow {
+        driver_num: u32,
+        buffer_num: u32,
+    },
+    ReadWriteAllow {
+        driver_num: u32,
+        buffer_num: u32,
+    },
+    AllowUserspaceReadable {
+        driver_num: u32,
+        buffer_num: u32,
+    },
+    Memop {
+        op: u32,
+    },
+    Exit {
+        which: u32,
+        completion: u32,
+    },
+}